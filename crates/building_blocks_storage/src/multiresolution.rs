@@ -0,0 +1,9 @@
+mod chunk_pyramid;
+#[cfg(feature = "gpu")]
+mod gpu_downsampler;
+mod shared_chunk_storage;
+
+pub use chunk_pyramid::*;
+#[cfg(feature = "gpu")]
+pub use gpu_downsampler::*;
+pub use shared_chunk_storage::*;
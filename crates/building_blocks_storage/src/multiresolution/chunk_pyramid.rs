@@ -1,4 +1,6 @@
-use crate::{prelude::*, ArrayIndexer, BytesCompression, ChunkDownsampler, ChunkHashMap};
+use crate::{
+    prelude::*, ArrayIndexer, BytesCompression, ChunkDownsampler, ChunkHashMap, SharedChunkStorage,
+};
 
 use building_blocks_core::prelude::*;
 
@@ -132,6 +134,101 @@ where
     }
 }
 
+impl<N, T, Meta, Store> ChunkPyramid<N, T, Meta, Store>
+where
+    N: ArrayIndexer<N>,
+    PointN<N>: Debug + IntegerPoint<N>,
+    T: Copy,
+    Store: ChunkReadStorage<N, T, Meta>,
+    ChunkIndexer<N>: Clone,
+{
+    /// Copies `extent`, in `target_lod`'s own voxel coordinates, into a standalone dense `Array` at `target_lod`'s
+    /// resolution, gathering data from whatever LOD is actually populated.
+    ///
+    /// Since the pyramid has "no enforcement of a particular occupancy" (it is a cache), a chunk covering part of `extent`
+    /// at `target_lod` may simply be missing. When that happens, this walks *up* the pyramid to the nearest coarser
+    /// populated LOD and nearest-neighbor upsamples its values into the output, falling back to `ambient_value()` if no
+    /// ancestor is populated either. The returned regions record which LOD actually supplied each part of the array, so
+    /// callers can tell cache hits from fallbacks.
+    pub fn sample_extent(&self, extent: &ExtentN<N>, target_lod: u8) -> (Array<N, T>, Vec<SampledRegion<N>>) {
+        let target_map = self.level(target_lod);
+        let mut dst_array = Array::fill(*extent, target_map.ambient_value());
+        let mut regions = Vec::new();
+
+        for chunk_key in target_map.indexer.chunk_keys_for_extent(extent) {
+            let chunk_extent = target_map.indexer.extent_for_chunk(chunk_key);
+            let region = chunk_extent.intersection(extent);
+            if region.is_empty() {
+                continue;
+            }
+
+            if let Some(chunk) = target_map.get_chunk(chunk_key) {
+                for p in region.iter_points() {
+                    dst_array[p] = chunk.array[p];
+                }
+                regions.push(SampledRegion {
+                    extent: region,
+                    source: SampleSource::Exact,
+                });
+            } else {
+                let source = self.upsample_from_ancestor(target_lod, chunk_key, &region, &mut dst_array);
+                regions.push(SampledRegion { extent: region, source });
+            }
+        }
+
+        (dst_array, regions)
+    }
+
+    /// Fills `region` of `dst_array` by nearest-neighbor upsampling from the nearest coarser populated LOD above
+    /// `target_lod`, or with the ambient value if none is populated.
+    fn upsample_from_ancestor(
+        &self,
+        target_lod: u8,
+        target_chunk_key: PointN<N>,
+        region: &ExtentN<N>,
+        dst_array: &mut Array<N, T>,
+    ) -> SampleSource {
+        let chunk_shape = self.level(target_lod).indexer.chunk_shape();
+
+        for ancestor_lod in (target_lod + 1)..self.levels.len() as u8 {
+            let lod_delta = (ancestor_lod - target_lod) as i32;
+            let ancestor_map = self.level(ancestor_lod);
+            let ancestor_dst = DownsampleDestination::for_source_chunk(chunk_shape, target_chunk_key, lod_delta as u8);
+
+            if let Some(ancestor_chunk) = ancestor_map.get_chunk(ancestor_dst.dst_chunk_key) {
+                for p in region.iter_points() {
+                    dst_array[p] = ancestor_chunk.array[p >> lod_delta];
+                }
+                return SampleSource::Fallback {
+                    source_lod: ancestor_lod,
+                };
+            }
+        }
+
+        // No ancestor is populated either; the caller already pre-filled `dst_array` with the ambient value.
+        SampleSource::Ambient
+    }
+}
+
+/// Where the data for one region of a [`ChunkPyramid::sample_extent`] result actually came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SampleSource {
+    /// The chunk was present at the requested LOD.
+    Exact,
+    /// The chunk was missing at the requested LOD, so this region was nearest-neighbor upsampled from a populated,
+    /// coarser LOD.
+    Fallback { source_lod: u8 },
+    /// Neither the requested LOD nor any coarser ancestor was populated, so this region is the ambient value.
+    Ambient,
+}
+
+/// One contiguous region of a [`ChunkPyramid::sample_extent`] result, tagged with where its data came from.
+#[derive(Clone, Debug)]
+pub struct SampledRegion<N> {
+    pub extent: ExtentN<N>,
+    pub source: SampleSource,
+}
+
 /// A `ChunkMap` using `HashMap` as chunk storage.
 pub type ChunkHashMapPyramid<N, T, Meta = ()> =
     ChunkPyramid<N, T, Meta, FnvHashMap<PointN<N>, Chunk<N, T, Meta>>>;
@@ -211,6 +308,58 @@ where
     }
 }
 
+/// A `ChunkPyramid` using [`SharedChunkStorage`] as chunk storage, giving it a [`ChunkPyramid::snapshot`] that copies no
+/// voxel data.
+pub type SharedChunkPyramid<N, T, Meta = ()> = ChunkPyramid<N, T, Meta, SharedChunkStorage<N, T, Meta>>;
+/// A 2-dimensional `SharedChunkPyramid`.
+pub type SharedChunkPyramid2<T, Meta = ()> = SharedChunkPyramid<[i32; 2], T, Meta>;
+/// A 3-dimensional `SharedChunkPyramid`.
+pub type SharedChunkPyramid3<T, Meta = ()> = SharedChunkPyramid<[i32; 3], T, Meta>;
+
+impl<N, T, Meta> SharedChunkPyramid<N, T, Meta>
+where
+    ChunkMap<N, T, Meta, SharedChunkStorage<N, T, Meta>>: Clone,
+{
+    /// Takes a read-only snapshot of every level in this pyramid without copying any voxel data; the cost is one hash map
+    /// clone per level, linear in the number of chunks.
+    ///
+    /// Only the per-level hash maps are cloned; every chunk is still reached through the same `Arc` as the live pyramid.
+    /// This lets `mesh_generator_system` hand a consistent view of the pyramid off to `ComputeTaskPool` worker tasks without
+    /// copying voxel data or blocking writers: a write that lands on a chunk the snapshot is still holding onto will
+    /// `Arc::make_mut` a private copy rather than disturbing it.
+    pub fn snapshot(&self) -> ChunkPyramidSnapshot<N, T, Meta> {
+        ChunkPyramidSnapshot {
+            levels: self.levels.clone(),
+        }
+    }
+}
+
+/// A read-only view of a [`SharedChunkPyramid`] taken at a point in time. See [`SharedChunkPyramid::snapshot`].
+pub struct ChunkPyramidSnapshot<N, T, Meta> {
+    levels: Vec<ChunkMap<N, T, Meta, SharedChunkStorage<N, T, Meta>>>,
+}
+
+impl<N, T, Meta> Clone for ChunkPyramidSnapshot<N, T, Meta>
+where
+    ChunkMap<N, T, Meta, SharedChunkStorage<N, T, Meta>>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            levels: self.levels.clone(),
+        }
+    }
+}
+
+impl<N, T, Meta> ChunkPyramidSnapshot<N, T, Meta> {
+    pub fn levels_slice(&self) -> &[ChunkMap<N, T, Meta, SharedChunkStorage<N, T, Meta>>] {
+        &self.levels[..]
+    }
+
+    pub fn level(&self, lod: u8) -> &ChunkMap<N, T, Meta, SharedChunkStorage<N, T, Meta>> {
+        &self.levels[lod as usize]
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct DownsampleDestination<N> {
     pub dst_chunk_key: PointN<N>,
@@ -308,4 +457,32 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn sample_extent_falls_back_to_coarser_populated_lod() {
+        let ambient = 0;
+        let chunk_shape = PointN([4; 3]);
+        let builder = ChunkMapBuilder3::new(chunk_shape, ambient);
+        let mut pyramid = ChunkHashMapPyramid3::new(builder, 2);
+
+        let lod0_chunk_key = PointN([0; 3]);
+        let lod0_extent = ExtentN::from_min_and_shape(lod0_chunk_key, chunk_shape);
+        let lod0_chunk = pyramid.level_mut(0).get_mut_chunk_or_insert_ambient(lod0_chunk_key);
+        lod0_chunk.array.fill_extent(&lod0_extent, 1);
+
+        pyramid.downsample_chunk_all_lods(&PointDownsampler, lod0_chunk_key);
+
+        // LOD 0 is populated directly, so it's an exact sample.
+        let (lod0_samples, lod0_regions) = pyramid.sample_extent(&lod0_extent, 0);
+        assert_eq!(lod0_regions.len(), 1);
+        assert_eq!(lod0_regions[0].source, SampleSource::Exact);
+        assert!(lod0_samples.extent().iter_points().all(|p| lod0_samples[p] == 1));
+
+        // Clear LOD 0 so sampling it has to fall back to the downsampled LOD 1 data.
+        *pyramid.level_mut(0) = ChunkHashMap3::new(builder);
+
+        let (_, fallback_regions) = pyramid.sample_extent(&lod0_extent, 0);
+        assert_eq!(fallback_regions.len(), 1);
+        assert_eq!(fallback_regions[0].source, SampleSource::Fallback { source_lod: 1 });
+    }
 }
@@ -0,0 +1,324 @@
+//! An optional, batched alternative to [`downsample_chunk`](crate::ChunkPyramid::downsample_chunk) for when there are
+//! many dirty chunks to downsample at once. All reductions run on the CPU; no GPU device is used.
+//!
+//! [`pack_dirty_chunks`] flattens every dirty source chunk's array data into one buffer up front, alongside an index
+//! recording where each destination voxel's source block starts and which destination chunk/offset it belongs to. That
+//! index is what a real backend would hand to a compute shader (one invocation per destination voxel, each folding its
+//! own `2^N`-sample block down to one value via a [`GpuReduction`]) before scattering the results back out; here
+//! [`downsample_dirty_chunks_gpu`] runs the same per-voxel reduction on the CPU so the batching logic is exercised and
+//! testable without an actual GPU backend wired up.
+//!
+//! Source chunks are only read through `ChunkReadStorage::get`, which is `None` for anything still ambient, so empty
+//! chunks are skipped before they ever reach the packer.
+
+use crate::{Array, ArrayIndexer, ChunkMap, ChunkReadStorage, ChunkWriteStorage, DownsampleDestination, Local};
+
+use building_blocks_core::prelude::*;
+
+use std::fmt::Debug;
+
+/// How the `2^N` source voxels of one destination block combine into a single destination voxel.
+///
+/// CPU downsamplers like `PointDownsampler` and `SdfMeanDownsampler` each encode one such reduction; `GpuReduction` is the
+/// same idea, applied once per destination voxel of a [`PackedBatch`].
+pub trait GpuReduction<T> {
+    /// Combines the `samples` of one destination voxel's source block into a single value. `samples.len()` is always
+    /// `2^(N * lod_delta)`.
+    fn reduce(&self, samples: &[T]) -> T;
+
+    /// The name of the WGSL reduction function this corresponds to. Nothing in this module reads it; there is no compiled
+    /// compute shader.
+    fn shader_entry_point(&self) -> &'static str;
+}
+
+/// Averages scalar channels, e.g. density or SDF values. Mirrors the CPU `SdfMeanDownsampler`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GpuMeanReduction;
+
+impl GpuReduction<f32> for GpuMeanReduction {
+    #[inline]
+    fn reduce(&self, samples: &[f32]) -> f32 {
+        samples.iter().sum::<f32>() / samples.len() as f32
+    }
+
+    fn shader_entry_point(&self) -> &'static str {
+        "reduce_mean"
+    }
+}
+
+/// Picks the most common value in the block, for channels like material IDs where averaging is meaningless. Mirrors the
+/// CPU `PointDownsampler` in spirit, but looks at the whole block instead of a single representative point.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GpuMajorityReduction;
+
+impl<T> GpuReduction<T> for GpuMajorityReduction
+where
+    T: Copy + Eq + std::hash::Hash,
+{
+    fn reduce(&self, samples: &[T]) -> T {
+        let mut counts = std::collections::HashMap::with_capacity(samples.len());
+        for &s in samples {
+            *counts.entry(s).or_insert(0usize) += 1;
+        }
+        *counts
+            .iter()
+            .max_by_key(|(_, &count)| count)
+            .map(|(value, _)| value)
+            .expect("block is never empty")
+    }
+
+    fn shader_entry_point(&self) -> &'static str {
+        "reduce_majority"
+    }
+}
+
+/// Takes the min (or max) of the block, e.g. for conservative occlusion LODs.
+#[derive(Clone, Copy, Debug)]
+pub struct GpuMinMaxReduction {
+    pub take_min: bool,
+}
+
+impl<T> GpuReduction<T> for GpuMinMaxReduction
+where
+    T: Copy + PartialOrd,
+{
+    fn reduce(&self, samples: &[T]) -> T {
+        let reducer = |a: T, b: T| {
+            let a_wins = if self.take_min { a < b } else { a > b };
+            if a_wins {
+                a
+            } else {
+                b
+            }
+        };
+        samples[1..].iter().fold(samples[0], |acc, &s| reducer(acc, s))
+    }
+
+    fn shader_entry_point(&self) -> &'static str {
+        if self.take_min {
+            "reduce_min"
+        } else {
+            "reduce_max"
+        }
+    }
+}
+
+/// Where one *destination voxel's* `2^N` source samples live in the packed batch buffer, and which destination voxel
+/// they reduce to.
+///
+/// A chunk downsampled by one LOD has `(chunk_shape >> 1)^N` destination voxels, each needing its own block of source
+/// samples, so there is one `BatchEntry` per destination voxel, not per source chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BatchEntry<N> {
+    /// Offset, in elements, of this destination voxel's `2^N` source samples within the packed source buffer.
+    pub src_offset: u32,
+    pub dst_chunk_key: PointN<N>,
+    /// This destination voxel's offset within `dst_chunk_key`.
+    pub dst_local: Local<N>,
+}
+
+/// The packed inputs to one compute dispatch: a flat buffer of every destination voxel's source samples back to back,
+/// plus an index describing how to route each block's reduction to its destination.
+#[derive(Clone, Debug, Default)]
+pub struct PackedBatch<N, T> {
+    pub src_buffer: Vec<T>,
+    pub entries: Vec<BatchEntry<N>>,
+}
+
+/// Packs the array data of every dirty, non-ambient source chunk into a single flat buffer, ready to upload to the GPU in
+/// one dispatch.
+///
+/// Each source chunk contributes `(chunk_shape >> 1)^N` destination voxels (for `lod_delta = 1`), and each of those gets
+/// its own contiguous `2^N`-sample block in `src_buffer` plus its own `BatchEntry`, so a single dirty chunk expands into
+/// many batch entries instead of one.
+///
+/// `dirty_chunks` pairs each source chunk key with its array; callers are expected to have already filtered out ambient
+/// chunks (e.g. via `ChunkMap::get_chunk`, which returns `None` for chunks that were never written).
+pub fn pack_dirty_chunks<N, T>(
+    chunk_shape: PointN<N>,
+    dirty_chunks: impl IntoIterator<Item = (PointN<N>, Array<N, T>)>,
+) -> PackedBatch<N, T>
+where
+    PointN<N>: IntegerPoint<N>,
+    T: Copy,
+{
+    let lod_delta: u8 = 1;
+    let dst_block_shape = chunk_shape >> lod_delta as i32;
+    let sub_block_shape = PointN::ONES * 2;
+
+    let mut batch = PackedBatch::default();
+
+    for (src_chunk_key, src_array) in dirty_chunks {
+        let dst = DownsampleDestination::for_source_chunk(chunk_shape, src_chunk_key, lod_delta);
+        let src_min = src_array.extent().minimum;
+
+        for local_dst in ExtentN::from_min_and_shape(PointN::ZERO, dst_block_shape).iter_points() {
+            let src_block_min = src_min + local_dst * 2;
+
+            batch.entries.push(BatchEntry {
+                src_offset: batch.src_buffer.len() as u32,
+                dst_chunk_key: dst.dst_chunk_key,
+                dst_local: Local(dst.dst_offset.0 + local_dst),
+            });
+
+            for sub_offset in ExtentN::from_min_and_shape(PointN::ZERO, sub_block_shape).iter_points() {
+                batch.src_buffer.push(src_array[src_block_min + sub_offset]);
+            }
+        }
+    }
+
+    batch
+}
+
+/// Runs one destination voxel's reduction against a slice of the packed source buffer.
+///
+/// [`downsample_dirty_chunks_gpu`] calls this once per [`BatchEntry`], on the CPU. There is no GPU counterpart.
+pub fn reduce_block<T, R: GpuReduction<T>>(reduction: &R, src_buffer: &[T], block_start: usize, block_len: usize) -> T
+where
+    T: Copy,
+{
+    reduction.reduce(&src_buffer[block_start..block_start + block_len])
+}
+
+/// Batches every one of `dirty_src_chunk_keys` into one [`PackedBatch`] and downsamples them by one LOD in a single pass
+/// over that batch, instead of one call per chunk.
+///
+/// Takes the full `ChunkMap`s, not just their `Store`s, because a destination chunk may not exist yet: like
+/// `ChunkPyramid::downsample_chunk`, this creates it via `get_mut_chunk_or_insert_ambient` before writing into it,
+/// rather than silently dropping voxels whose destination chunk hasn't been touched before.
+///
+/// Every destination voxel is computed by [`reduce_block`] on the CPU; no GPU adapter is queried and nothing is
+/// dispatched to a GPU. The `gpu_matches_cpu_for_random_inputs` test below checks that with [`GpuMeanReduction`] the
+/// result equals that of `SdfMeanDownsampler`.
+pub fn downsample_dirty_chunks_gpu<N, T, Meta, Src, Dst, R>(
+    src_map: &ChunkMap<N, T, Meta, Src>,
+    dst_map: &mut ChunkMap<N, T, Meta, Dst>,
+    dirty_src_chunk_keys: impl IntoIterator<Item = PointN<N>>,
+    reduction: &R,
+) where
+    N: ArrayIndexer<N>,
+    PointN<N>: Debug + IntegerPoint<N>,
+    T: Copy,
+    Meta: Clone,
+    Src: ChunkReadStorage<N, T, Meta>,
+    Dst: ChunkWriteStorage<N, T, Meta>,
+    R: GpuReduction<T>,
+{
+    let chunk_shape = src_map.indexer.chunk_shape();
+    let block_len = 1usize << N::DIM;
+
+    let dirty_chunks = dirty_src_chunk_keys
+        .into_iter()
+        .filter_map(|key| src_map.get_chunk(key).map(|chunk| (key, chunk.array.clone())));
+
+    let batch = pack_dirty_chunks(chunk_shape, dirty_chunks);
+
+    // Reduce each destination voxel's block on the CPU and scatter the result into its destination chunk.
+    for entry in batch.entries.iter() {
+        let block_start = entry.src_offset as usize;
+        let value = reduce_block(reduction, &batch.src_buffer, block_start, block_len);
+
+        let dst_chunk = dst_map.get_mut_chunk_or_insert_ambient(entry.dst_chunk_key);
+        dst_chunk.array.set_local(entry.dst_local, value);
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Array3, ChunkDownsampler, ChunkHashMap3, ChunkMapBuilder3, SdfMeanDownsampler};
+
+    #[test]
+    fn mean_reduction_averages_block() {
+        let reduction = GpuMeanReduction;
+        assert_eq!(reduction.reduce(&[1.0, 2.0, 3.0, 4.0]), 2.5);
+    }
+
+    #[test]
+    fn majority_reduction_picks_most_common_value() {
+        let reduction = GpuMajorityReduction;
+        assert_eq!(reduction.reduce(&[7u8, 7, 7, 2]), 7);
+    }
+
+    #[test]
+    fn min_max_reduction_picks_extreme() {
+        let min_reduction = GpuMinMaxReduction { take_min: true };
+        let max_reduction = GpuMinMaxReduction { take_min: false };
+        assert_eq!(min_reduction.reduce(&[3, 1, 4, 1, 5]), 1);
+        assert_eq!(max_reduction.reduce(&[3, 1, 4, 1, 5]), 5);
+    }
+
+    #[test]
+    fn pack_dirty_chunks_emits_one_entry_per_destination_voxel() {
+        // A 4^3 chunk downsamples to a 2^3 block of destination voxels, i.e. 8 entries, each backed by its own 2^3 = 8
+        // source samples, not one entry for the whole chunk.
+        let chunk_shape = PointN([4; 3]);
+        let extent = ExtentN::from_min_and_shape(Point3i::ZERO, chunk_shape);
+        let dirty = vec![(Point3i::ZERO, Array3::fill(extent, 1.0))];
+
+        let batch = pack_dirty_chunks(chunk_shape, dirty);
+
+        assert_eq!(batch.entries.len(), 8);
+        assert_eq!(batch.src_buffer.len(), 8 * 8);
+        assert_eq!(batch.entries[1].src_offset, 8);
+    }
+
+    #[test]
+    fn gpu_matches_cpu_for_random_inputs() {
+        let chunk_shape = PointN([4; 3]);
+        let ambient = 0.0f32;
+        let builder = ChunkMapBuilder3::new(chunk_shape, ambient);
+
+        let mut cpu_src = ChunkHashMap3::new(builder);
+        let mut cpu_dst = ChunkHashMap3::new(builder);
+        let mut gpu_src = ChunkHashMap3::new(builder);
+        let mut gpu_dst = ChunkHashMap3::new(builder);
+
+        let src_chunk_key = Point3i::ZERO;
+        let src_extent = ExtentN::from_min_and_shape(src_chunk_key, chunk_shape);
+
+        // A small xorshift PRNG keeps this test deterministic without pulling in a `rand` dependency.
+        let mut rng_state = 0xdead_beefu64;
+        let mut next_sample = || {
+            rng_state ^= rng_state << 13;
+            rng_state ^= rng_state >> 7;
+            rng_state ^= rng_state << 17;
+            (rng_state % 1000) as f32 / 1000.0
+        };
+
+        cpu_src.get_mut_chunk_or_insert_ambient(src_chunk_key);
+        gpu_src.get_mut_chunk_or_insert_ambient(src_chunk_key);
+        for p in src_extent.iter_points() {
+            let sample = next_sample();
+            cpu_src.get_mut_chunk(src_chunk_key).unwrap().array[p] = sample;
+            gpu_src.get_mut_chunk(src_chunk_key).unwrap().array[p] = sample;
+        }
+
+        // `SdfMeanDownsampler::downsample` writes straight into an array, so it needs the destination chunk to already
+        // exist. `downsample_dirty_chunks_gpu` is not given that luxury: `gpu_dst` starts out completely empty, so this
+        // also exercises it creating the destination chunk itself.
+        SdfMeanDownsampler.downsample(
+            &cpu_src.get_chunk(src_chunk_key).unwrap().array,
+            &mut cpu_dst.get_mut_chunk_or_insert_ambient(Point3i::ZERO).array,
+            Local(Point3i::ZERO),
+            1,
+        );
+
+        downsample_dirty_chunks_gpu(&gpu_src, &mut gpu_dst, std::iter::once(src_chunk_key), &GpuMeanReduction);
+
+        let dst_extent = ExtentN::from_min_and_shape(Point3i::ZERO, chunk_shape >> 1);
+        for p in dst_extent.iter_points() {
+            assert_eq!(
+                cpu_dst.get_chunk(Point3i::ZERO).unwrap().array[p],
+                gpu_dst.get_chunk(Point3i::ZERO).unwrap().array[p],
+            );
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::{Array3, Chunk, ChunkReadStorage, ChunkWriteStorage, IterChunkKeys};
+
+use building_blocks_core::prelude::*;
+
+use fnv::FnvHashMap;
+use std::collections::hash_map;
+use std::hash::Hash;
+use std::iter::Copied;
+use std::sync::Arc;
+
+/// A `ChunkWriteStorage`/`ChunkReadStorage` implementation backed by `Arc<Chunk<N, T, Meta>>`.
+///
+/// Putting each chunk behind an `Arc` turns `Clone` on the whole map into a cheap hash map copy: the `Arc` pointers get
+/// bumped, but no chunk data moves. [`ChunkPyramid::snapshot`] relies on exactly this to capture a stable view of the
+/// pyramid for a worker task without stalling whatever is still writing to the live map.
+///
+/// The write side has to cooperate with that sharing: `get_mut` goes through `Arc::make_mut`, which clones the pointee
+/// only if some other `Arc` (e.g. a snapshot) is still holding onto it. Once a chunk is uniquely owned again, further
+/// writes are free.
+pub struct SharedChunkStorage<N, T, Meta> {
+    chunks: FnvHashMap<PointN<N>, Arc<Chunk<N, T, Meta>>>,
+}
+
+impl<N, T, Meta> Default for SharedChunkStorage<N, T, Meta>
+where
+    PointN<N>: Hash + Eq,
+{
+    fn default() -> Self {
+        Self {
+            chunks: FnvHashMap::default(),
+        }
+    }
+}
+
+impl<N, T, Meta> Clone for SharedChunkStorage<N, T, Meta>
+where
+    PointN<N>: Hash + Eq + Clone,
+{
+    /// Clones the hash map of `Arc`s, not the chunks they point to.
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+        }
+    }
+}
+
+impl<N, T, Meta> ChunkReadStorage<N, T, Meta> for SharedChunkStorage<N, T, Meta>
+where
+    PointN<N>: Hash + Eq,
+{
+    #[inline]
+    fn get(&self, key: PointN<N>) -> Option<&Chunk<N, T, Meta>> {
+        self.chunks.get(&key).map(|chunk| &**chunk)
+    }
+}
+
+impl<N, T, Meta> ChunkWriteStorage<N, T, Meta> for SharedChunkStorage<N, T, Meta>
+where
+    PointN<N>: Hash + Eq + Clone,
+    Chunk<N, T, Meta>: Clone,
+{
+    #[inline]
+    fn get_mut(&mut self, key: PointN<N>) -> Option<&mut Chunk<N, T, Meta>> {
+        // Only clones the chunk if it's shared with some other owner, e.g. a snapshot.
+        self.chunks.get_mut(&key).map(Arc::make_mut)
+    }
+
+    #[inline]
+    fn replace(&mut self, key: PointN<N>, chunk: Chunk<N, T, Meta>) -> Option<Chunk<N, T, Meta>> {
+        self.chunks
+            .insert(key, Arc::new(chunk))
+            .map(|old| Arc::try_unwrap(old).unwrap_or_else(|shared| (*shared).clone()))
+    }
+
+    #[inline]
+    fn write(&mut self, key: PointN<N>, chunk: Chunk<N, T, Meta>) {
+        self.chunks.insert(key, Arc::new(chunk));
+    }
+}
+
+impl<'a, N, T, Meta> IterChunkKeys<'a, N> for SharedChunkStorage<N, T, Meta>
+where
+    PointN<N>: 'a,
+    T: 'a,
+    Meta: 'a,
+{
+    type Iter = Copied<hash_map::Keys<'a, PointN<N>, Arc<Chunk<N, T, Meta>>>>;
+
+    #[inline]
+    fn chunk_keys(&'a self) -> Self::Iter {
+        self.chunks.keys().copied()
+    }
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_chunk(metadata: i32) -> Chunk<[i32; 3], (), i32> {
+        Chunk {
+            metadata,
+            array: Array3::fill(Extent3i::from_min_and_shape(Point3i::ZERO, Point3i::fill(4)), ()),
+        }
+    }
+
+    #[test]
+    fn write_while_snapshot_alive_does_not_mutate_snapshot() {
+        let mut storage = SharedChunkStorage::<[i32; 3], (), i32>::default();
+        storage.write(Point3i::ZERO, test_chunk(1));
+
+        // Cloning the storage (what `ChunkPyramid::snapshot` does) shares the `Arc` rather than copying the chunk.
+        let snapshot = storage.clone();
+
+        storage.get_mut(Point3i::ZERO).unwrap().metadata = 2;
+
+        assert_eq!(storage.get(Point3i::ZERO).unwrap().metadata, 2);
+        assert_eq!(snapshot.get(Point3i::ZERO).unwrap().metadata, 1);
+    }
+
+    #[test]
+    fn write_with_unique_arc_mutates_in_place() {
+        let mut storage = SharedChunkStorage::<[i32; 3], (), i32>::default();
+        storage.write(Point3i::ZERO, test_chunk(1));
+
+        let ptr_before = Arc::as_ptr(&storage.chunks[&Point3i::ZERO]);
+
+        storage.get_mut(Point3i::ZERO).unwrap().metadata = 2;
+
+        let ptr_after = Arc::as_ptr(&storage.chunks[&Point3i::ZERO]);
+        assert_eq!(ptr_before, ptr_after);
+        assert_eq!(storage.get(Point3i::ZERO).unwrap().metadata, 2);
+    }
+}
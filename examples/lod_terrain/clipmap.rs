@@ -0,0 +1,195 @@
+use crate::mesh_generator::{MeshCommand, MeshCommands};
+use crate::voxel_map::VoxelMap;
+
+use bevy_utilities::bevy::{prelude::*, render::camera::Camera};
+use building_blocks::{mesh::*, prelude::*, storage::SmallKeyHashSet};
+
+/// Tracks which chunks are currently considered "active" (i.e. should have a mesh) at each LOD, so
+/// `clipmap_update_system` can diff the camera's new position against the previous frame's state instead of
+/// recomputing the whole clipmap from scratch.
+pub struct ClipmapState {
+    /// Active chunk keys, indexed by LOD. `active_chunks[0]` holds the finest, un-split active chunks.
+    active_chunks: Vec<SmallKeyHashSet<Point3i>>,
+    /// `split_radii[lod]` is the distance from the camera at which an active chunk at `lod` splits into its `2^N`
+    /// children at `lod - 1`.
+    split_radii: Vec<f32>,
+    /// `merge_radii[lod]` is the distance at which a full set of sibling children at `lod` merges back into their
+    /// parent at `lod + 1`. Always greater than `split_radii[lod + 1]`, so a chunk has to move well past the radius that
+    /// would split it again before it's allowed to merge, preventing thrashing as the camera jitters near a boundary.
+    merge_radii: Vec<f32>,
+}
+
+impl ClipmapState {
+    /// Starts with only the single coarsest-LOD chunk covering the origin considered active; the first call to
+    /// `clipmap_update_system` will split it down towards the camera.
+    pub fn new(num_lods: u8, split_radii: Vec<f32>, merge_radii: Vec<f32>) -> Self {
+        assert_eq!(split_radii.len(), num_lods as usize);
+        assert_eq!(merge_radii.len(), num_lods as usize);
+        assert!(
+            split_radii[1..].iter().zip(merge_radii.iter()).all(|(s, m)| s < m),
+            "merge_radii[lod] must be strictly greater than split_radii[lod + 1] to provide hysteresis"
+        );
+
+        let mut active_chunks = vec![SmallKeyHashSet::default(); num_lods as usize];
+        active_chunks[num_lods as usize - 1].insert(Point3i::ZERO);
+
+        Self {
+            active_chunks,
+            split_radii,
+            merge_radii,
+        }
+    }
+
+    fn num_lods(&self) -> u8 {
+        self.active_chunks.len() as u8
+    }
+}
+
+/// The `2^N` children of `parent_key`, one LOD finer. Inverse of `parent_key`, which maps a child's key to its parent's
+/// the same way `DownsampleDestination::for_source_chunk` does for a `lod_delta` of 1.
+///
+/// Chunk keys are chunk-shape-aligned origins in their own LOD's voxel scale (see
+/// `DownsampleDestination::for_source_chunk`, whose destination key is always a multiple of `chunk_shape`), so siblings
+/// differ by a full `chunk_shape` per axis, not by 1 raw coordinate unit.
+fn child_keys(parent_key: Point3i, chunk_shape: Point3i) -> [Point3i; 8] {
+    let base = parent_key * 2;
+    let mut children = [Point3i::ZERO; 8];
+    let mut i = 0;
+    for dz in 0..2 {
+        for dy in 0..2 {
+            for dx in 0..2 {
+                children[i] = base + PointN([dx, dy, dz]) * chunk_shape;
+                i += 1;
+            }
+        }
+    }
+    children
+}
+
+/// Inverse of `child_keys`: the chunk-shape-aligned key of the parent chunk, one LOD coarser, that `child_key` belongs to.
+fn parent_key(child_key: Point3i, chunk_shape: Point3i) -> Point3i {
+    ((child_key / chunk_shape) >> 1) * chunk_shape
+}
+
+/// Computes the split/merge operations needed to maintain a distance-based detail falloff around the camera, and emits
+/// them as `MeshCommand::LodChange`s so `mesh_generator_system` regenerates exactly the chunks that changed LOD.
+pub fn clipmap_update_system<Map: VoxelMap>(
+    cameras: Query<(&Camera, &Transform)>,
+    voxel_map: Res<Map>,
+    mesh_commands: Res<MeshCommands>,
+    mut clipmap_state: ResMut<ClipmapState>,
+) {
+    let camera_position = if let Some((_camera, tfm)) = cameras.iter().next() {
+        Point3f::from(tfm.translation)
+    } else {
+        return;
+    };
+
+    let indexer = voxel_map.chunk_indexer();
+    let chunk_shape = indexer.chunk_shape();
+    let num_lods = clipmap_state.num_lods();
+
+    let mut splits = Vec::new();
+    let mut merges = Vec::new();
+
+    // Splits: walk from the coarsest LOD down, since a chunk can only split into the next finer LOD once per frame.
+    for lod in (1..num_lods).rev() {
+        let split_radius = clipmap_state.split_radii[lod as usize];
+        let to_split: Vec<Point3i> = clipmap_state.active_chunks[lod as usize]
+            .iter()
+            .copied()
+            .filter(|&key| {
+                let chunk_key = ChunkKey3::new(lod, key);
+                distance_to_camera(&indexer, chunk_key, camera_position) < split_radius
+            })
+            .collect();
+
+        for key in to_split {
+            clipmap_state.active_chunks[lod as usize].remove(&key);
+            let children = child_keys(key, chunk_shape);
+            clipmap_state.active_chunks[lod as usize - 1].extend(children.iter().copied());
+
+            splits.push(MeshCommand::LodChange(LodChange3::Split(SplitChunk {
+                old_chunk: ChunkKey3::new(lod, key),
+                new_chunks: children.map(|c| ChunkKey3::new(lod - 1, c)),
+            })));
+        }
+    }
+
+    // Merges: walk from the finest LOD up, applying each LOD's merges to `active_chunks` before scanning the next, so a
+    // parent that just absorbed its children at `lod` is immediately a candidate child for merging at `lod + 1` in the
+    // same frame (mirroring how the splits loop above cascades down through the LODs it touches).
+    for lod in 0..num_lods - 1 {
+        let merge_radius = clipmap_state.merge_radii[lod as usize];
+
+        let mut candidate_parents: SmallKeyHashSet<Point3i> = SmallKeyHashSet::default();
+        for &key in clipmap_state.active_chunks[lod as usize].iter() {
+            candidate_parents.insert(parent_key(key, chunk_shape));
+        }
+
+        for parent in candidate_parents {
+            let children = child_keys(parent, chunk_shape);
+            let active = &clipmap_state.active_chunks[lod as usize];
+            let all_children_active = children.iter().all(|c| active.contains(c));
+            if !all_children_active {
+                continue;
+            }
+
+            let all_beyond_merge_radius = children.iter().all(|&c| {
+                let chunk_key = ChunkKey3::new(lod, c);
+                distance_to_camera(&indexer, chunk_key, camera_position) > merge_radius
+            });
+            if !all_beyond_merge_radius {
+                continue;
+            }
+
+            for c in children.iter() {
+                clipmap_state.active_chunks[lod as usize].remove(c);
+            }
+            clipmap_state.active_chunks[lod as usize + 1].insert(parent);
+
+            merges.push(MeshCommand::LodChange(LodChange3::Merge(MergeChunks {
+                old_chunks: children.map(|c| ChunkKey3::new(lod, c)),
+                new_chunk: ChunkKey3::new(lod + 1, parent),
+                new_chunk_is_bounded: true,
+            })));
+        }
+    }
+
+    mesh_commands.add_commands(splits.into_iter());
+    mesh_commands.add_commands(merges.into_iter());
+}
+
+fn distance_to_camera(indexer: &ChunkIndexer3, chunk_key: ChunkKey3, camera_position: Point3f) -> f32 {
+    let chunk_sphere = chunk_lod0_bounding_sphere(indexer, chunk_key);
+    (chunk_sphere.center - camera_position).norm()
+}
+
+// ████████╗███████╗███████╗████████╗
+// ╚══██╔══╝██╔════╝██╔════╝╚══██╔══╝
+//    ██║   █████╗  ███████╗   ██║
+//    ██║   ██╔══╝  ╚════██║   ██║
+//    ██║   ███████╗███████║   ██║
+//    ╚═╝   ╚══════╝╚══════╝   ╚═╝
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parent_key_is_inverse_of_child_keys() {
+        let chunk_shape = Point3i::fill(16);
+
+        for parent in [
+            Point3i::ZERO,
+            PointN([16, 0, 0]),
+            PointN([-16, 32, 0]),
+            PointN([48, -16, 80]),
+        ] {
+            let children = child_keys(parent, chunk_shape);
+            for child in children.iter() {
+                assert_eq!(parent_key(*child, chunk_shape), parent);
+            }
+        }
+    }
+}